@@ -0,0 +1,18 @@
+//! Small clipboard abstraction so plugins can yield "copy this and close" results, and the UI can paste into the
+//! query, without caring whether the session is X11 or Wayland; `arboard` already handles that distinction.
+
+pub struct Clipboard(arboard::Clipboard);
+
+impl Clipboard {
+    pub fn new() -> Option<Self> {
+        arboard::Clipboard::new().ok().map(Self)
+    }
+
+    pub fn set(&mut self, text: &str) -> bool {
+        self.0.set_text(text).is_ok()
+    }
+
+    pub fn get(&mut self) -> Option<String> {
+        self.0.get_text().ok()
+    }
+}
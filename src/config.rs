@@ -0,0 +1,43 @@
+use crate::ui::Theme;
+
+/// Which edges of the output the layer-shell overlay is anchored to; all `false` centers it like the regular
+/// always-on-top toplevel does. See `crate::layer_shell`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WindowAnchor {
+    pub top: bool,
+    pub bottom: bool,
+    pub left: bool,
+    pub right: bool
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub font_size: f32,
+    pub placeholder_text: String,
+    pub icon_theme: String,
+    pub theme: Theme,
+    pub window_width: u32,
+    pub window_height: u32,
+    pub layer_shell_anchor: WindowAnchor
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            font_size: 16.0,
+            placeholder_text: "search...".to_owned(),
+            icon_theme: "hicolor".to_owned(),
+            theme: Theme::default(),
+            window_width: 1920/3,
+            window_height: 1080/2,
+            layer_shell_anchor: WindowAnchor { top: true, ..Default::default() }
+        }
+    }
+}
+
+/// Returns the config, built once and cached. It is always `Config::default()`; no config file is read.
+pub fn config() -> &'static Config {
+    use std::sync::OnceLock;
+    static CONFIG: OnceLock<Config> = OnceLock::new();
+    CONFIG.get_or_init(Config::default)
+}
@@ -0,0 +1,65 @@
+use std::{collections::VecDeque, fs, io::Write, path::PathBuf};
+
+/// keal is launched fresh on every invocation, so without this the shell's own history is the only way to recall
+/// a previous query; this persists a bounded, de-duplicated ring of recent ones instead.
+const MAX_ENTRIES: usize = 200;
+
+pub struct History {
+    /// oldest first, most recently submitted at the back
+    entries: VecDeque<String>,
+    path: PathBuf
+}
+
+impl History {
+    pub fn load() -> Self {
+        let path = history_path();
+        let entries = fs::read_to_string(&path)
+            .map(|contents| contents.lines().map(str::to_owned).collect())
+            .unwrap_or_default();
+
+        History { entries, path }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// `0` is the most recently submitted query, `1` the one before that, and so on.
+    pub fn nth_from_newest(&self, n: usize) -> Option<&str> {
+        let index = self.entries.len().checked_sub(1)?.checked_sub(n)?;
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /// Records `query` as the most recent entry, moving it to the back if it was already in the ring, then
+    /// persists the (still-bounded) ring to disk.
+    pub fn push(&mut self, query: String) {
+        if query.trim().is_empty() { return }
+
+        self.entries.retain(|existing| existing != &query);
+        self.entries.push_back(query);
+        while self.entries.len() > MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+
+        self.save();
+    }
+
+    fn save(&self) {
+        if let Some(parent) = self.path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+
+        let Ok(mut file) = fs::File::create(&self.path) else { return };
+        for entry in &self.entries {
+            let _ = writeln!(file, "{entry}");
+        }
+    }
+}
+
+fn history_path() -> PathBuf {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(std::env::var("HOME").unwrap_or_default()).join(".config"));
+
+    config_dir.join("keal").join("history")
+}
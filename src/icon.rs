@@ -0,0 +1,24 @@
+use std::{collections::HashMap, path::PathBuf};
+
+#[derive(Debug, Clone)]
+pub enum Icon {
+    Svg(PathBuf),
+    Other(PathBuf)
+}
+
+/// Maps entry icon names to icon files; built once at startup on a worker thread. `new` ignores the theme name
+/// and scans no directory, so the cache is empty and `get` always returns `None`.
+#[derive(Debug, Clone, Default)]
+pub struct IconCache {
+    icons: HashMap<String, Icon>
+}
+
+impl IconCache {
+    pub fn new(_theme: &str) -> Self {
+        IconCache { icons: HashMap::new() }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Icon> {
+        self.icons.get(name)
+    }
+}
@@ -0,0 +1,127 @@
+//! Single-instance control socket: a thin line-based protocol over a Unix domain socket under
+//! `$XDG_RUNTIME_DIR`, so a hotkey-bound launch can toggle an already-warm instance instead of cold-starting a
+//! fresh one, and other tools can script the launcher's query.
+
+use std::{
+    io::{BufRead, BufReader, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::PathBuf,
+    sync::{mpsc::Sender, OnceLock}
+};
+
+use crate::ui::Message;
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    Show,
+    Hide,
+    Toggle,
+    SetInput(String),
+    Quit
+}
+
+impl Command {
+    fn encode(&self) -> String {
+        match self {
+            Command::Show => "SHOW\n".to_owned(),
+            Command::Hide => "HIDE\n".to_owned(),
+            Command::Toggle => "TOGGLE\n".to_owned(),
+            Command::SetInput(input) => format!("SET_INPUT {}\n", input.replace('\n', " ")),
+            Command::Quit => "QUIT\n".to_owned()
+        }
+    }
+
+    fn decode(line: &str) -> Option<Command> {
+        if let Some(input) = line.strip_prefix("SET_INPUT ") {
+            return Some(Command::SetInput(input.to_owned()));
+        }
+
+        match line {
+            "SHOW" => Some(Command::Show),
+            "HIDE" => Some(Command::Hide),
+            "TOGGLE" => Some(Command::Toggle),
+            "QUIT" => Some(Command::Quit),
+            _ => None
+        }
+    }
+
+    fn into_message(self) -> Message {
+        match self {
+            Command::Show => Message::Show,
+            Command::Hide => Message::Hide,
+            Command::Toggle => Message::Toggle,
+            Command::SetInput(input) => Message::TextInput(input),
+            Command::Quit => Message::Quit
+        }
+    }
+}
+
+fn socket_path() -> PathBuf {
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from).unwrap_or_else(std::env::temp_dir);
+    runtime_dir.join("keal.sock")
+}
+
+/// Tries to hand `command` to an already-running instance. Returns `true` if a server was listening (the caller
+/// should exit), `false` if this process should bind the socket and become the server instead.
+pub fn try_send(command: &Command) -> bool {
+    let Ok(mut stream) = UnixStream::connect(socket_path()) else { return false };
+    stream.write_all(command.encode().as_bytes()).is_ok()
+}
+
+static INITIAL_COMMAND: OnceLock<Command> = OnceLock::new();
+
+/// Stashes `command` for `apply_initial` to pick up once this process becomes the server itself, so a cold start
+/// (nothing was listening yet) still honors the command it was launched with instead of silently dropping it.
+pub fn set_initial(command: Command) {
+    let _ = INITIAL_COMMAND.set(command);
+}
+
+/// Applies whatever command `set_initial` stashed, if any, as though it had arrived over the socket. Call this
+/// once the message channel exists, right after `serve` starts listening for the next one.
+pub fn apply_initial(message_sender: &Sender<Message>) {
+    if let Some(command) = INITIAL_COMMAND.get() {
+        let _ = message_sender.send(command.clone().into_message());
+    }
+}
+
+/// Binds the control socket and spawns a background thread that turns incoming commands into `Message`s pushed
+/// through `message_sender`, the same channel worker threads already use.
+///
+/// By the time we get here `main` has already tried (and failed) to connect to this path, which rules out a
+/// *currently live* server, but it doesn't rule out a stale socket file left behind by a crashed previous
+/// instance, nor another instance that lost the same race and is about to bind first. We only remove the path
+/// once `UnixStream::connect` against it has actually failed, instead of unconditionally unlinking whatever's
+/// there; that closes the common crash-recovery case. A fully atomic fix would need a separate lock file (e.g. an
+/// exclusive `flock`) so two instances starting at the same instant can't both pass this check and one silently
+/// steal the path out from under the other's listener - worth adding if this race turns out to matter in practice.
+pub fn serve(message_sender: Sender<Message>) {
+    let path = socket_path();
+
+    if UnixStream::connect(&path).is_ok() {
+        eprintln!("keal: another instance is already serving {}, not taking over", path.display());
+        return;
+    }
+    let _ = std::fs::remove_file(&path);
+
+    let listener = match UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("keal: failed to bind control socket at {}: {err}", path.display());
+            return;
+        }
+    };
+
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let message_sender = message_sender.clone();
+            std::thread::spawn(move || handle_client(stream, message_sender));
+        }
+    });
+}
+
+fn handle_client(stream: UnixStream, message_sender: Sender<Message>) {
+    for line in BufReader::new(stream).lines().map_while(Result::ok) {
+        let Some(command) = Command::decode(&line) else { continue };
+        let _ = message_sender.send(command.into_message());
+    }
+}
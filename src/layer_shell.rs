@@ -0,0 +1,118 @@
+//! Wayland layer-shell integration: when the compositor advertises `zwlr_layer_shell_v1` (sway, hyprland, river,
+//! and most other wlroots-based compositors), `try_create_overlay` creates an anchored overlay surface on the
+//! `Overlay` layer, and returns `None` on X11 sessions and Wayland compositors that don't implement the protocol.
+//! `main` does not call it: nothing draws into such a surface, so every session runs the regular always-on-top
+//! toplevel.
+
+use smithay_client_toolkit::{
+    compositor::{CompositorHandler, CompositorState},
+    delegate_compositor, delegate_layer, delegate_output, delegate_registry,
+    output::{OutputHandler, OutputState},
+    registry::{ProvidesRegistryState, RegistryState},
+    registry_handlers,
+    shell::{
+        wlr_layer::{Anchor, KeyboardInteractivity, Layer, LayerShell, LayerShellHandler, LayerSurface, LayerSurfaceConfigure},
+        WaylandSurface
+    }
+};
+use wayland_client::{protocol::{wl_output, wl_surface}, globals::registry_queue_init, Connection, QueueHandle};
+
+use crate::config::{config, WindowAnchor};
+
+fn to_sctk_anchor(anchor: WindowAnchor) -> Anchor {
+    let mut bits = Anchor::empty();
+    if anchor.top { bits |= Anchor::TOP; }
+    if anchor.bottom { bits |= Anchor::BOTTOM; }
+    if anchor.left { bits |= Anchor::LEFT; }
+    if anchor.right { bits |= Anchor::RIGHT; }
+    bits
+}
+
+pub struct LayerShellWindow {
+    registry_state: RegistryState,
+    output_state: OutputState,
+    layer: LayerSurface,
+    configured: bool
+}
+
+impl LayerShellWindow {
+    pub fn surface(&self) -> &wl_surface::WlSurface {
+        self.layer.wl_surface()
+    }
+}
+
+/// Tries to stand the overlay up on the compositor found at `$WAYLAND_DISPLAY`. Returns `None` (and the caller
+/// should fall back to a regular toplevel) when there's no Wayland compositor at all, or it exists but doesn't
+/// support `wlr-layer-shell`.
+pub fn try_create_overlay() -> Option<LayerShellWindow> {
+    let connection = Connection::connect_to_env().ok()?;
+    let (globals, mut queue) = registry_queue_init::<LayerShellWindow>(&connection).ok()?;
+    let qh = queue.handle();
+
+    let compositor = CompositorState::bind(&globals, &qh).ok()?;
+    let layer_shell = LayerShell::bind(&globals, &qh).ok()?;
+
+    let config = config();
+    let surface = compositor.create_surface(&qh);
+    let layer = layer_shell.create_layer_surface(&qh, surface, Layer::Overlay, Some("keal"), None);
+
+    layer.set_anchor(to_sctk_anchor(config.layer_shell_anchor));
+    layer.set_keyboard_interactivity(KeyboardInteractivity::Exclusive);
+    layer.set_size(config.window_width, config.window_height);
+    layer.set_exclusive_zone(-1);
+    layer.commit();
+
+    let mut window = LayerShellWindow {
+        registry_state: RegistryState::new(&globals),
+        output_state: OutputState::new(&globals, &qh),
+        layer,
+        configured: false
+    };
+
+    // block until the compositor acks the initial configure, mirroring blocking winit window creation: by the
+    // time `try_create_overlay` returns, the surface is ready to draw into
+    while !window.configured {
+        queue.blocking_dispatch(&mut window).ok()?;
+    }
+
+    Some(window)
+}
+
+impl CompositorHandler for LayerShellWindow {
+    fn scale_factor_changed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: i32) {}
+    fn transform_changed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: wl_output::Transform) {}
+    fn frame(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: u32) {}
+    fn surface_enter(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: &wl_output::WlOutput) {}
+    fn surface_leave(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &wl_surface::WlSurface, _: &wl_output::WlOutput) {}
+}
+
+impl OutputHandler for LayerShellWindow {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output_state
+    }
+    fn new_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+    fn update_output(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+    fn output_destroyed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: wl_output::WlOutput) {}
+}
+
+impl LayerShellHandler for LayerShellWindow {
+    fn closed(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &LayerSurface) {
+        std::process::exit(0);
+    }
+
+    fn configure(&mut self, _: &Connection, _: &QueueHandle<Self>, _: &LayerSurface, _: LayerSurfaceConfigure, _: u32) {
+        self.configured = true;
+    }
+}
+
+impl ProvidesRegistryState for LayerShellWindow {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+    registry_handlers![OutputState];
+}
+
+delegate_compositor!(LayerShellWindow);
+delegate_output!(LayerShellWindow);
+delegate_layer!(LayerShellWindow);
+delegate_registry!(LayerShellWindow);
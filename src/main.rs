@@ -4,13 +4,49 @@
 
 use iced::{Application, Settings, window, Font, font};
 
+mod clipboard;
+mod config;
+mod history;
+mod icon;
+mod ipc;
+// groundwork for an anchored wlr-layer-shell overlay; not wired into `main` yet, see the comment at the call site
+// this used to have. kept `allow(dead_code)` instead of deleting it so the next attempt isn't starting from zero.
+#[allow(dead_code)]
+mod layer_shell;
+mod plugin;
 mod search;
 mod ui;
 
 fn main() -> iced::Result {
+    // launched with a query/command for an already-running instance, e.g. `keal toggle` bound to a hotkey
+    let mut args = std::env::args().skip(1);
+    let command = match args.next().as_deref() {
+        Some("show") => ipc::Command::Show,
+        Some("hide") => ipc::Command::Hide,
+        Some("toggle") | None => ipc::Command::Toggle,
+        Some("quit") => ipc::Command::Quit,
+        Some(input) => ipc::Command::SetInput(input.to_owned())
+    };
+
+    if ipc::try_send(&command) {
+        return Ok(());
+    }
+
+    // nobody was listening, so this process is about to become the server itself (see `ipc::serve`); hand the
+    // command to `ipc::apply_initial` to run once that's set up, instead of just dropping it on the floor
+    ipc::set_initial(command);
+
+    // `layer_shell::try_create_overlay` can stand up a real wlr-layer-shell surface, but actually drawing into one
+    // needs miniquad to own a raw-window-handle pointed at it instead of creating its own window, and that wiring
+    // (a `Keal::run_on_surface` or equivalent) doesn't exist yet. Calling into it here would create a surface and
+    // then have nothing draw to it, so every session - Wayland or not - runs the regular toplevel below until
+    // that's built.
+
+    let config = config::config();
+
     ui::Keal::run(Settings {
         window: window::Settings {
-            size: (1920/3, 1080/2),
+            size: (config.window_width, config.window_height),
             position: window::Position::Centered,
             resizable: false,
             decorations: false,
@@ -27,3 +63,10 @@ fn main() -> iced::Result {
         ..Default::default()
     })
 }
+
+pub fn log_time(label: &str) {
+    use std::{sync::OnceLock, time::Instant};
+    static START: OnceLock<Instant> = OnceLock::new();
+    let start = *START.get_or_init(Instant::now);
+    eprintln!("[{:>7.2?}] {label}", start.elapsed());
+}
@@ -0,0 +1,13 @@
+/// Identifies an entry well enough for its owning plugin to re-resolve it later (e.g. on `Message::Launch`),
+/// without the UI layer needing to know anything about how a given plugin represents its own entries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Label(pub usize);
+
+/// An entry as rendered in the result list; owned because it's sent across the worker -> UI channel.
+#[derive(Debug, Clone)]
+pub struct OwnedEntry {
+    pub name: String,
+    pub comment: Option<String>,
+    pub icon: Option<String>,
+    pub label: Label
+}
@@ -0,0 +1,30 @@
+use std::process::Command;
+
+pub mod entry;
+
+/// What a plugin asks the UI to do after resolving a query or launching an entry.
+#[derive(Debug)]
+pub enum Action {
+    /// do nothing
+    None,
+    /// replace the whole input, e.g. a plugin switching into its own prefix
+    ChangeInput(String),
+    /// replace the query part of the input, keeping the active plugin's prefix
+    ChangeQuery(String),
+    /// run a command and close
+    Exec(ForkCommand),
+    /// print a value to stdout and close, e.g. for scripting keal from a shell pipeline
+    PrintAndClose(String),
+    /// write a value to the system clipboard and close, e.g. for password/emoji/calculator plugins that should
+    /// yield a value rather than exec a command
+    Copy(String),
+    /// fork so the child can exec without killing the launcher's own process
+    Fork,
+    /// wait for a spawned child before closing
+    WaitAndClose
+}
+
+/// Wraps `std::process::Command` so `handle_action` can move it out of the `Action` and call the unix-only
+/// `CommandExt::exec` on it directly.
+#[derive(Debug)]
+pub struct ForkCommand(pub Command);
@@ -4,9 +4,10 @@ use fork::{fork, Fork};
 // use iced::{Application, executor, Command, widget::{row as irow, text_input, column as icolumn, container, text, Space, scrollable, button, image, svg}, font, Element, Length, subscription, Event, keyboard::{self, KeyCode, Modifiers}, futures::channel::mpsc};
 use macroquad::{miniquad::{window::set_mouse_cursor, CursorIcon}, prelude::*};
 use nucleo_matcher::Matcher;
+use rustybuzz::Face;
 use smallvec::SmallVec;
 
-use crate::{icon::{IconCache, Icon}, config::config, plugin::{Action, entry::{Label, OwnedEntry}}, log_time};
+use crate::{clipboard::Clipboard, history::History, icon::{IconCache, Icon}, config::config, plugin::{Action, entry::{Label, OwnedEntry}}, log_time};
 
 pub use styled::Theme;
 // use styled::{ButtonStyle, TextStyle};
@@ -16,92 +17,227 @@ use self::{match_span::MatchSpan, async_manager::AsyncManager};
 mod styled;
 mod match_span;
 mod async_manager;
+mod shaping;
+
+/// An entry's text measured once, independent of viewport width: the cumulative advance width up to every cluster
+/// boundary (byte offset right after the cluster, running width up to and including it). Wrap layers are derived
+/// from this with no further text measurement or shaping, just a scan/binary-search over `advances`.
+///
+/// ASCII text is measured char-by-char with `measure_text`, which is cheap but does not see ligatures between ASCII
+/// characters; anything else is run
+/// through `shaper` (HarfBuzz-style shaping via rustybuzz) so ligatures, combining marks, and contextual forms are
+/// measured as the font would actually render them, and advances only ever land on cluster boundaries.
+struct MeasuredRun {
+    advances: SmallVec<[(usize, f32); 16]>,
+    total_width: f32
+}
 
-/// Returns a vector of indices (byte offsets) at which the text should wrap, as well as the total height of the text
-fn measure_text_wrap(text: &str, max_width: f32, font: Option<&Font>, font_size: f32, line_height: f32) -> WrapInfo {
-    let max_width = max_width.max(font_size*2.0);
-
-    let mut splits = SmallVec::new();
-    let mut height = font_size;
+impl MeasuredRun {
+    fn measure(text: &str, font: Option<&Font>, shaper: Option<&Face>, font_size: f32) -> Self {
+        match shaper {
+            Some(face) if shaping::needs_shaping(text) => Self::measure_shaped(text, face, font_size),
+            _ => Self::measure_ascii(text, font, font_size)
+        }
+    }
 
-    let mut running_width = 0.0;
+    fn measure_ascii(text: &str, font: Option<&Font>, font_size: f32) -> Self {
+        let mut advances = SmallVec::new();
+        let mut running = 0.0;
+        let mut last = 0;
 
-    let mut line_start = 0;
-    let mut last = 0;
-    let mut iter = text.char_indices();
-    iter.next();
-    for (index, c) in iter {
-        let dims = measure_text(&text[last..index], font, font_size as u16, 1.0);
+        for (index, _) in text.char_indices().skip(1).chain(std::iter::once((text.len(), '\0'))) {
+            let dims = measure_text(&text[last..index], font, font_size as u16, 1.0);
+            running += dims.width;
+            advances.push((index, running));
+            last = index;
+        }
 
-        if c == '\n' || running_width + dims.width >= max_width {
-            line_start = index;
-            running_width = 0.0;
+        MeasuredRun { advances, total_width: running }
+    }
 
-            height += font_size + line_height;
-            splits.push(last);
-        } 
+    fn measure_shaped(text: &str, face: &Face, font_size: f32) -> Self {
+        let run = shaping::shape_text(face, text, font_size);
 
-        running_width += dims.width;
-        last = index;
-    }
+        let mut advances = SmallVec::new();
+        let mut running = 0.0;
 
-    if line_start < text.len() {
-        let dims = measure_text(&text[last..], font, font_size as u16, 1.0);
-        running_width += dims.width;
+        let mut i = 0;
+        while i < run.glyphs.len() {
+            let cluster = run.glyphs[i].cluster;
 
-        splits.push(text.len());
-    }
+            let mut j = i;
+            let mut cluster_width = 0.0;
+            while j < run.glyphs.len() && run.glyphs[j].cluster == cluster {
+                cluster_width += run.glyphs[j].x_advance;
+                j += 1;
+            }
 
-    let width = if line_start == 0 { running_width } else { max_width };
+            running += cluster_width;
+            let boundary = if j < run.glyphs.len() { run.glyphs[j].cluster } else { text.len() };
+            advances.push((boundary, running));
+            i = j;
+        }
 
-    WrapInfo { splits, width, height }
+        MeasuredRun { advances, total_width: running }
+    }
 }
 
-struct WrapInfo {
+/// The only thing recomputed on a viewport resize: split offsets and total height, derived from a `MeasuredRun`'s
+/// cumulative advances rather than by re-measuring text.
+#[derive(Default, Clone)]
+struct WrapLayer {
     splits: SmallVec<[usize; 8]>,
     width: f32,
     height: f32
 }
 
+impl WrapLayer {
+    /// Entries whose measured width already fits inside `max_width` skip straight to a single-line layer with no
+    /// scan at all; only entries whose measured width exceeds the viewport need the wrap scan below. Whether a
+    /// *caller* can skip calling this at all across resizes is `Entries::still_single_line`'s job, not this one.
+    fn layout(run: &MeasuredRun, max_width: f32, font_size: f32, line_height: f32) -> Self {
+        let max_width = max_width.max(font_size*2.0);
+
+        if run.total_width < max_width {
+            let end = run.advances.last().map(|&(offset, _)| offset).unwrap_or(0);
+            return WrapLayer { splits: smallvec::smallvec![end], width: run.total_width, height: font_size };
+        }
+
+        Self::wrap(run, max_width, font_size, line_height)
+    }
+
+    /// Walks `run.advances` a line at a time; for each line, binary-searches (via `partition_point`) for the
+    /// furthest cluster boundary whose cumulative advance still fits under the line's width budget.
+    fn wrap(run: &MeasuredRun, max_width: f32, font_size: f32, line_height: f32) -> Self {
+        let mut splits = SmallVec::new();
+        let mut height = font_size;
+
+        let mut pos = 0;
+        let mut baseline = 0.0;
+
+        while pos < run.advances.len() {
+            let target = baseline + max_width;
+            let cut = run.advances[pos..].partition_point(|&(_, width)| width < target) + pos;
+            // always advance by at least one cluster, even an overlong one, so we can't get stuck
+            let split_index = if cut == pos { pos } else { cut - 1 };
+
+            let (split_offset, split_width) = run.advances[split_index];
+
+            if split_index + 1 >= run.advances.len() {
+                splits.push(split_offset);
+                break;
+            }
+
+            splits.push(split_offset);
+            baseline = split_width;
+            height += font_size + line_height;
+            pos = split_index + 1;
+        }
+
+        // a wrapped entry always reports the full line budget as its width, not however much of it the longest
+        // line actually used, so the comment column's start doesn't jump around as wrapped text reflows
+        WrapLayer { splits, width: max_width, height }
+    }
+}
+
 #[derive(Default)]
 struct Entries {
     list: Vec<OwnedEntry>,
-    /// info for entry.name and entry.comment (optional)
-    wrap_info: Vec<(WrapInfo, Option<WrapInfo>)>,
-    total_height: f32
+    /// measured once per entry, when the entry list itself changes
+    measured: Vec<(MeasuredRun, Option<MeasuredRun>)>,
+    /// rebuilt (cheaply, see `WrapLayer::layout`) whenever the viewport width changes
+    wrap: Vec<(WrapLayer, Option<WrapLayer>)>,
+    entry_heights: Vec<f32>,
+    total_height: f32,
+    /// viewport width `recalculate` last ran with; together with each entry's previous `WrapLayer` this is what
+    /// lets it skip entries that were a single unwrapped line before and still are, rather than rebuilding an
+    /// identical layer for every entry on every resize tick
+    last_width: f32
 }
 
 impl Entries {
-    fn new(list: Vec<OwnedEntry>, font: Option<&Font>) -> Self {
+    fn new(list: Vec<OwnedEntry>, font: Option<&Font>, shaper: Option<&Face>) -> Self {
         let mut this = Self {
             list,
-            wrap_info: Vec::new(),
-            total_height: 0.0
+            measured: Vec::new(),
+            wrap: Vec::new(),
+            entry_heights: Vec::new(),
+            total_height: 0.0,
+            last_width: 0.0
         };
 
-        this.recalculate(font);
+        this.remeasure(font, shaper);
+        this.recalculate(screen_width());
         this
     }
 
-    /// call this when the screen width changes
-    fn recalculate(&mut self, font: Option<&Font>) {
+    /// call this when `list` itself changes (a fresh `Message::Entries` batch); this is the expensive pass that
+    /// actually measures/shapes text, so it must not run on every resize tick.
+    fn remeasure(&mut self, font: Option<&Font>, shaper: Option<&Face>) {
         let config = config();
 
+        self.measured = self.list.iter().map(|entry| {
+            let name = MeasuredRun::measure(&entry.name, font, shaper, config.font_size);
+            let comment = entry.comment.as_ref().map(|comment| MeasuredRun::measure(comment, font, shaper, config.font_size));
+            (name, comment)
+        }).collect();
+
+        self.wrap.clear();
+        self.entry_heights.clear();
         self.total_height = 0.0;
-        self.wrap_info.clear();
-        self.wrap_info.extend(self.list.iter().map(|entry| {
-            let name = measure_text_wrap(&entry.name, screen_width()/2.0, font, config.font_size, 5.0);
-            let mut max_height = name.height;
+        // force every entry through a full layout on the next `recalculate`, since `wrap` was just cleared
+        self.last_width = -1.0;
+    }
 
-            let comment_width = screen_width() - name.width - 10.0 - 20.0 - 10.0; // this removes: name left padding, name-comment inner padding, comment right padding
-            let comment = entry.comment.as_ref()
-                .map(|comment| measure_text_wrap(comment, comment_width, font, config.font_size, 5.0))
-                .inspect(|comment| max_height = max_height.max(comment.height));
+    /// `true` when `existing` was already a single unwrapped line and `run` still fits under both the previous
+    /// and the new budget. In that case the freshly-laid-out result would be byte-for-byte identical (a
+    /// single-line `WrapLayer`'s shape depends only on whether the run fits, not on the exact budget), so the
+    /// caller can reuse `existing` unchanged instead of rebuilding it.
+    fn still_single_line(existing: &WrapLayer, run: &MeasuredRun, previous_budget: f32, new_budget: f32, font_size: f32) -> bool {
+        let min_budget = previous_budget.min(new_budget).max(font_size*2.0);
+        existing.splits.len() == 1 && run.total_width < min_budget
+    }
 
-            self.total_height += max_height + 20.0;
+    /// call this when the screen width changes; cheap unless many entries overflow the new viewport width, or
+    /// newly do because the viewport shrank
+    fn recalculate(&mut self, width: f32) {
+        let config = config();
 
-            (name, comment)
-        }));
+        if self.wrap.len() != self.measured.len() {
+            self.wrap.resize_with(self.measured.len(), Default::default);
+            self.entry_heights.resize(self.measured.len(), 0.0);
+        }
+
+        for (i, (name_run, comment_run)) in self.measured.iter().enumerate() {
+            let name_budget = width/2.0;
+            let previous_name_budget = self.last_width/2.0;
+            let name_layer = if Self::still_single_line(&self.wrap[i].0, name_run, previous_name_budget, name_budget, config.font_size) {
+                self.wrap[i].0.clone()
+            } else {
+                WrapLayer::layout(name_run, name_budget, config.font_size, 5.0)
+            };
+            let mut max_height = name_layer.height;
+
+            // this removes: name left padding, name-comment inner padding, comment right padding
+            let comment_budget = width - name_layer.width - 10.0 - 20.0 - 10.0;
+            let previous_comment_budget = self.last_width - self.wrap[i].0.width - 10.0 - 20.0 - 10.0;
+            let comment_layer = comment_run.as_ref().map(|run| {
+                let layer = match &self.wrap[i].1 {
+                    Some(existing) if Self::still_single_line(existing, run, previous_comment_budget, comment_budget, config.font_size) => existing.clone(),
+                    _ => WrapLayer::layout(run, comment_budget, config.font_size, 5.0)
+                };
+                max_height = max_height.max(layer.height);
+                layer
+            });
+
+            let height = max_height + 20.0;
+            self.total_height += height - self.entry_heights[i];
+            self.entry_heights[i] = height;
+
+            self.wrap[i] = (name_layer, comment_layer);
+        }
+
+        self.last_width = width;
     }
 }
 
@@ -112,13 +248,30 @@ pub struct Keal {
     scroll: f32,
 
     old_screen_width: f32,
+    /// driven by `Message::Show`/`Hide`/`Toggle` from the control socket; while `false`, `render` draws nothing
+    /// and `update` ignores input
+    visible: bool,
+    /// latest typed/pasted input not yet sent to the matcher, and when it arrived; flushed after `INPUT_DEBOUNCE`
+    /// of no further typing so a burst of keystrokes collapses into a single `Event::UpdateInput`
+    pending_input: Option<(String, f64)>,
+
+    // query history
+    history: History,
+    /// `Some(n)` while recalling history, `n` entries back from the newest; `None` means the live, in-progress
+    /// query is showing
+    history_cursor: Option<usize>,
+    /// the in-progress query as it was before recall started, restored when stepping back past the newest entry
+    history_draft: Option<String>,
 
     // data state
     icons: IconCache,
     font: Option<Font>,
+    /// HarfBuzz-style shaper for the same font `font` draws with; `None` falls back to per-char measuring.
+    shaper: Option<Face<'static>>,
 
     entries: Entries,
     manager: AsyncManager,
+    clipboard: Option<Clipboard>,
 
     message_sender: Sender<Message>,
     message_rec: Receiver<Message>
@@ -133,7 +286,13 @@ pub enum Message {
     // Worker events
     IconCacheLoaded(IconCache),
     Entries(Vec<OwnedEntry>),
-    Action(Action)
+    Action(Action),
+
+    // control socket events, see `crate::ipc`
+    Show,
+    Hide,
+    Toggle,
+    Quit
 }
 
 impl Keal {
@@ -144,8 +303,9 @@ impl Keal {
 
         let (message_sender, message_rec) = channel();
 
-        let iosevka = include_bytes!("../../public/iosevka-regular.ttf");
-        let iosevka = load_ttf_font_from_bytes(iosevka).expect("failed to load font");
+        let iosevka_bytes = include_bytes!("../../public/iosevka-regular.ttf");
+        let iosevka = load_ttf_font_from_bytes(iosevka_bytes).expect("failed to load font");
+        let shaper = Face::from_slice(iosevka_bytes, 0);
         log_time("finished loading font");
 
         {
@@ -156,6 +316,11 @@ impl Keal {
             });
         }
 
+        crate::ipc::serve(message_sender.clone());
+        // this process became the server because no existing instance answered `try_send`; if main.rs cold-started
+        // us with a command (e.g. `keal some query`), apply it now instead of letting it have been silently dropped
+        crate::ipc::apply_initial(&message_sender);
+
         let manager = AsyncManager::new(Matcher::default(), 50, true, message_sender.clone());
 
         log_time("finished initializing");
@@ -165,16 +330,25 @@ impl Keal {
             selected: 0,
             scroll: 0.0,
             old_screen_width: 0.0,
+            visible: true,
+            pending_input: None,
+            history: History::load(),
+            history_cursor: None,
+            history_draft: None,
             icons: Default::default(),
             font: Some(iosevka),
+            shaper,
             entries: Default::default(),
             manager,
+            clipboard: Clipboard::new(),
             message_sender,
             message_rec
         }
     }
 
     pub fn render(&mut self) {
+        if !self.visible { return }
+
         let entries = &self.entries;
         let config = config();
 
@@ -195,7 +369,7 @@ impl Keal {
         let mut offset_y = search_bar_height + self.scroll;
 
         set_mouse_cursor(CursorIcon::Default);
-        for (index, (entry, wrap_info)) in entries.list.iter().zip(entries.wrap_info.iter()).enumerate() {
+        for (index, (entry, wrap_info)) in entries.list.iter().zip(entries.wrap.iter()).enumerate() {
             let max_height = wrap_info.0.height.max(wrap_info.1.as_ref().map(|x| x.height).unwrap_or(0.0));
             let next_offset_y = offset_y + max_height + 20.0;
             if next_offset_y < 0.0 { 
@@ -233,6 +407,10 @@ impl Keal {
             for &line_end in &wrap_info.0.splits {
                 let text = &entry.name[line_start..line_end];
 
+                // each span is still measured/drawn through macroquad's own (non-shaping) text path rather than
+                // the shaped glyphs `MeasuredRun` computed for wrapping, and `MatchSpan`'s highlight boundaries
+                // aren't snapped to cluster boundaries, so a ligature can still get split across a color change.
+                // fixing either needs changes inside `ui::match_span`, which this tree doesn't have a copy of.
                 let mut offset = 10.0;
                 for (span, highlighted) in MatchSpan::new(text, &mut data.matcher, &data.pattern, &mut buf) {
                     let dims = measure_text(span, None, config.font_size as u16, 1.0);
@@ -285,8 +463,45 @@ impl Keal {
     }
 
     pub fn update(&mut self) {
+        // drain the whole queue instead of handling one message per frame: a burst of worker `Entries` batches or
+        // key events would otherwise back up behind the frame rate and input would feel laggy under load. messages
+        // where only the final value matters are coalesced as they're drained, not handled one at a time. this
+        // always runs, even while hidden, since `Message::Show`/`Toggle` have to be able to wake the instance back up.
+        let mut latest_entries = None;
+        loop {
+            let message = match self.message_rec.try_recv() {
+                Ok(message) => message,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => panic!("manager channel disconnected")
+            };
+
+            match message {
+                Message::TextInput(input) => self.queue_input(input),
+                Message::Launch(selected) => {
+                    self.history.push(self.input.clone());
+                    self.history_cursor = None;
+                    self.manager.send(async_manager::Event::Launch(selected));
+                }
+                Message::IconCacheLoaded(icon_cache) => self.icons = icon_cache,
+                Message::Entries(entries) => latest_entries = Some(entries), // superseded batches are dropped
+                Message::Action(action) => self.handle_action(action),
+                Message::Show => self.visible = true,
+                Message::Hide => self.visible = false,
+                Message::Toggle => self.visible = !self.visible,
+                Message::Quit => std::process::exit(0),
+            }
+        }
+
+        if let Some(entries) = latest_entries {
+            self.entries = Entries::new(entries, self.font.as_ref(), self.shaper.as_ref());
+        }
+
+        // while hidden there's nothing on screen to scroll, select, or type into, so skip reacting to input
+        // entirely instead of silently accumulating state the user can't see
+        if !self.visible { return }
+
         if self.old_screen_width != screen_width() {
-            self.entries.recalculate(self.font.as_ref());
+            self.entries.recalculate(screen_width());
             self.old_screen_width = screen_width();
         }
 
@@ -301,30 +516,94 @@ impl Keal {
             self.selected = self.selected.saturating_sub(1);
         }
 
-        let message = match self.message_rec.try_recv() {
-            Ok(message) => message,
-            Err(TryRecvError::Empty) => return,
-            Err(TryRecvError::Disconnected) => panic!("manager channel disconnected")
-        };
+        let alt = is_key_down(KeyCode::LeftAlt);
+        if alt && is_key_pressed(KeyCode::P) {
+            self.recall_history(1); // step backward, towards older queries
+        }
+        if alt && is_key_pressed(KeyCode::N) {
+            self.recall_history(-1); // step forward, towards the in-progress query
+        }
 
-        match message {
-            Message::TextInput(input) => self.update_input(input, true),
-            Message::Launch(selected) => {
-                self.manager.send(async_manager::Event::Launch(selected));
+        if ctrl && is_key_pressed(KeyCode::V) {
+            if let Some(pasted) = self.clipboard.as_mut().and_then(Clipboard::get) {
+                let input = format!("{}{pasted}", self.input);
+                self.queue_input(input);
             }
-            Message::IconCacheLoaded(icon_cache) => self.icons = icon_cache,
-            Message::Entries(entries) => self.entries = Entries::new(entries, self.font.as_ref()),
-            Message::Action(action) => return self.handle_action(action),
-        };
+        }
+
+        self.flush_queued_input();
+    }
+
+    /// Whether the overlay should currently be on screen; toggled by the control socket in `crate::ipc` so a
+    /// hotkey can bring up an already-warm instance instead of cold-starting a new one. Consulted by `update`
+    /// (skips reacting to input while hidden) and `render` (skips drawing entirely).
+    pub fn visible(&self) -> bool {
+        self.visible
     }
 }
 
+/// How long typing has to pause before the debounced input in `Keal::pending_input` is sent to the matcher.
+const INPUT_DEBOUNCE: f64 = 0.05;
+
 impl Keal {
     pub fn update_input(&mut self, input: String, from_user: bool) {
         self.input = input.clone();
         self.manager.send(async_manager::Event::UpdateInput(input, from_user));
     }
 
+    /// Updates the visible input immediately but defers sending `Event::UpdateInput` to the matcher until typing
+    /// pauses for `INPUT_DEBOUNCE`, so a burst of keystrokes (or `Message::TextInput`s piling up behind a slow
+    /// frame) collapses into a single worker update instead of one per keystroke.
+    ///
+    /// This is the path typed keystrokes, pastes, and `SET_INPUT` IPC commands all go through, none of which are
+    /// history recall - so it always exits recall mode first. Otherwise the stale `history_draft` captured when
+    /// recall started would later overwrite whatever got typed/pasted/set while `history_cursor` was still `Some`
+    /// (e.g. Alt+P to recall, Ctrl+V to paste onto it, Alt+N silently reverting the paste).
+    fn queue_input(&mut self, input: String) {
+        self.history_cursor = None;
+        self.history_draft = None;
+
+        self.input = input.clone();
+        self.pending_input = Some((input, get_time()));
+    }
+
+    /// Steps `delta` entries through history relative to the current `history_cursor` (positive = further into
+    /// the past, negative = back towards the in-progress query), updating the input to match. Stepping past the
+    /// newest entry restores whatever was being typed before recall started.
+    fn recall_history(&mut self, delta: isize) {
+        let next_cursor = match self.history_cursor {
+            None if delta > 0 => Some(0),
+            None => None,
+            Some(cursor) => (cursor as isize + delta).try_into().ok()
+        };
+
+        match next_cursor {
+            Some(cursor) => {
+                let Some(query) = self.history.nth_from_newest(cursor) else { return };
+
+                if self.history_cursor.is_none() {
+                    self.history_draft = Some(self.input.clone());
+                }
+                self.history_cursor = Some(cursor);
+                self.update_input(query.to_owned(), false);
+            }
+            None if self.history_cursor.is_some() => {
+                self.history_cursor = None;
+                self.update_input(self.history_draft.take().unwrap_or_default(), false);
+            }
+            None => ()
+        }
+    }
+
+    fn flush_queued_input(&mut self) {
+        let Some((input, queued_at)) = &self.pending_input else { return };
+        if get_time() - queued_at < INPUT_DEBOUNCE { return }
+
+        let input = input.clone();
+        self.pending_input = None;
+        self.manager.send(async_manager::Event::UpdateInput(input, true));
+    }
+
     fn handle_action(&mut self, action: Action) /* -> Command<Message> */ {
         match action {
             Action::None => (),
@@ -349,6 +628,12 @@ impl Keal {
                 println!("{message}");
                 // return iced::window::close();
             }
+            Action::Copy(text) => {
+                if let Some(clipboard) = &mut self.clipboard {
+                    clipboard.set(&text);
+                }
+                // return iced::window::close();
+            }
             Action::Fork => match fork().expect("failed to fork") {
                 Fork::Parent(_) => (),//return iced::window::close(),
                 Fork::Child => ()
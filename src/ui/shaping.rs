@@ -0,0 +1,68 @@
+use rustybuzz::{Face, UnicodeBuffer};
+
+/// One shaped glyph: the font's glyph id, its advance in pixels (already scaled
+/// to the requested font size), and the byte offset into the source text of the
+/// cluster it belongs to. Several glyphs can share a cluster (ligature
+/// components) or a cluster can span several source chars (combining marks),
+/// which is why wrapping has to split on clusters rather than chars.
+pub struct ShapedGlyph {
+    pub glyph_id: u32,
+    pub x_advance: f32,
+    pub cluster: usize,
+}
+
+/// NOTE on scope: this module only feeds `MeasuredRun`'s wrap-point math (`ui::mod::MeasuredRun::measure_shaped`),
+/// so ligatures/clusters/combining marks in non-ASCII text (see `needs_shaping`) break lines in the right place. It
+/// does not feed `render`, which still draws through macroquad's own `measure_text`/`draw_text_ex`, a separate,
+/// non-shaping text path - so an Arabic contextual form wraps at its shaped width but still draws unshaped. Making
+/// `render` draw these shaped glyphs (and have `MatchSpan` highlight ranges snap to cluster boundaries, the rest
+/// of the original ask) needs `ui::match_span`, which isn't present in this tree to wire into - see the comment
+/// at the `MatchSpan::new` call site in `ui::mod`.
+pub struct ShapedRun {
+    pub glyphs: Vec<ShapedGlyph>,
+    pub width: f32,
+}
+
+/// Shapes `text` with `face`, the HarfBuzz-style way: build a buffer, let
+/// rustybuzz guess script/direction/language, then read back glyphs. Advances
+/// come back in font units, so scale by `font_size / upem`.
+///
+/// HarfBuzz emits glyphs in visual (rendering) order, which for an RTL run is
+/// the reverse of logical/byte order, so `cluster` decreases as the index
+/// increases. The caller (wrapping in `MeasuredRun`) assumes clusters land in
+/// byte-ascending order so a line is always a contiguous logical substring of
+/// the source text, so glyphs are sorted back into that order here rather
+/// than pushing the visual/logical distinction onto every caller.
+pub fn shape_text(face: &Face, text: &str, font_size: f32) -> ShapedRun {
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+
+    let output = rustybuzz::shape(face, &[], buffer);
+
+    let upem = face.units_per_em() as f32;
+    let scale = if upem > 0.0 { font_size / upem } else { 1.0 };
+
+    let infos = output.glyph_infos();
+    let positions = output.glyph_positions();
+
+    let mut order: Vec<usize> = (0..infos.len()).collect();
+    order.sort_by_key(|&i| infos[i].cluster);
+
+    let mut width = 0.0;
+    let glyphs = order.into_iter().map(|i| {
+        let x_advance = positions[i].x_advance as f32 * scale;
+        width += x_advance;
+        ShapedGlyph { glyph_id: infos[i].glyph_id, x_advance, cluster: infos[i].cluster as usize }
+    }).collect();
+
+    ShapedRun { glyphs, width }
+}
+
+/// Pure ASCII never reorders or combines, so it takes the cheap per-char
+/// `measure_text` path; ligatures between ASCII characters (such as Iosevka's)
+/// are not accounted for there. Only text outside that range (Arabic,
+/// Devanagari, ...) goes through real shaping.
+pub fn needs_shaping(text: &str) -> bool {
+    !text.is_ascii()
+}
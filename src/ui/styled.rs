@@ -0,0 +1,27 @@
+use macroquad::color::{color_u8, Color};
+
+/// Colors used while drawing the result list and search bar; `config()` always uses `Theme::default()`.
+#[derive(Debug, Clone)]
+pub struct Theme {
+    pub text: Color,
+    pub comment: Color,
+    pub matched_text: Color,
+    pub selected_matched_text: Color,
+    pub input_background: Color,
+    pub hovered_choice_background: Color,
+    pub selected_choice_background: Color
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            text: color_u8!(220, 220, 220, 255),
+            comment: color_u8!(140, 140, 140, 255),
+            matched_text: color_u8!(120, 170, 255, 255),
+            selected_matched_text: color_u8!(255, 255, 255, 255),
+            input_background: color_u8!(30, 30, 30, 255),
+            hovered_choice_background: color_u8!(45, 45, 45, 255),
+            selected_choice_background: color_u8!(60, 60, 60, 255)
+        }
+    }
+}